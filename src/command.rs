@@ -1,10 +1,12 @@
-use std::{ops::Deref, process};
+use std::{ops::Deref, path::Path, process};
 
-use git2::Repository;
 use regex::Regex;
-use url::Url;
 
-use crate::DEBUG;
+use crate::{
+    conflict, history, oplog,
+    repository::{self, Repository},
+    rewrite, status, DEBUG,
+};
 
 #[derive(clap::Subcommand, Debug, Clone)]
 pub enum Command {
@@ -77,68 +79,154 @@ pub enum Command {
 impl Command {
     pub fn perform(self) -> Result<(), String> {
         match self {
-            Command::Clone { url } => git(&["clone", &url]),
+            Command::Clone { url } => {
+                let destination = repository_name(&url)
+                    .map_err(|_| format!("Could not determine a directory name for {}", url))?;
+                repository::clone(&url, Path::new(&destination))
+            }
             Command::Sync => {
                 let sync_info = sync()?;
                 print_sync_info(sync_info);
 
                 Ok(())
             }
-            Command::Status => {
-                let branch_name = get_branch_name()?;
-                let output = git_with_output(&["status", "--short"])?;
-
-                println!("On branch {}", branch_name);
-                println!("{}", output);
-
-                Ok(())
+            Command::Status => status::status(),
+            Command::History => history::history(),
+            Command::Stage { pattern } => with_oplog("stage", None, None, || stage(&pattern)),
+            Command::Unstage { pattern } => with_oplog("unstage", None, None, || unstage(&pattern)),
+            Command::Clear => {
+                let stash_ref = stash_branch_changes(false)?;
+                with_oplog("clear", stash_ref, None, || git(&["reset", "--hard"]))
             }
-            Command::History => todo!(),
-            Command::Stage { pattern } => stage(&pattern),
-            Command::Unstage { pattern } => unstage(&pattern),
-            Command::Clear => git(&["reset", "--hard"]),
-            Command::Commit { message } => {
+            Command::Commit { message } => with_oplog("commit", None, None, || {
                 git(&["commit", "-m", &message])?;
                 let sync_info = sync()?;
                 print_sync_info(sync_info);
 
                 Ok(())
+            }),
+            Command::Switch { branch_name } => {
+                with_oplog("switch", None, Some(&branch_name), || switch(&branch_name))
             }
-            Command::Switch { branch_name } => switch(&branch_name),
             Command::Branch { branch_name } => {
-                stash_branch_changes(true)?;
-                git(&["checkout", "-b", &branch_name])?;
-                Ok(())
+                let stash_ref = stash_branch_changes(true)?;
+                with_oplog("branch", stash_ref, Some(&branch_name), || {
+                    repository::open()?.create_branch(&branch_name)
+                })
             }
-            Command::Undo => todo!(),
-            Command::Redo => todo!(),
-            Command::Rewrite => todo!(), // TODO: Present interactive TUI instead of using system editor
-            Command::Rebase { other_branch } => {
+            Command::Undo => oplog::undo(),
+            Command::Redo => oplog::redo(),
+            Command::Rewrite => with_oplog("rewrite", None, None, rewrite::rewrite),
+            Command::Rebase { other_branch } => with_oplog("rebase", None, None, || {
                 let current_branch = get_branch_name()?;
                 switch(&other_branch)?;
                 sync()?;
                 switch(&current_branch)?;
-                git(&["rebase", &other_branch])?; // TODO: Handle merge conflicts somehow
+
+                if let Some(failure_stderr) = rebase_needs_resolution(&other_branch)? {
+                    if conflict::resolve("rebase", &failure_stderr)? == conflict::Outcome::Aborted {
+                        println!("Rebase aborted; branch left as it was before the rebase");
+                    }
+                }
 
                 Ok(())
-            }
+            }),
         }
     }
 }
 
-fn print_sync_info((ahead, behind): (usize, usize)) {
-    if ahead == 0 && behind == 0 {
-        println!("Already up to date");
-    } else {
-        println!("Pushed {} commits and pulled {} commits", ahead, behind);
+/// Run a mutating command and record it in the operation log so it can
+/// later be undone/redone, capturing the current branch and HEAD oid
+/// before the command runs and the HEAD oid after. `target_branch` is the
+/// branch a `switch`/`branch` command moves to, so undo/redo can check it
+/// out directly instead of resetting a ref.
+fn with_oplog<F>(
+    command_name: &str,
+    stash_ref: Option<String>,
+    target_branch: Option<&str>,
+    f: F,
+) -> Result<(), String>
+where
+    F: FnOnce() -> Result<(), String>,
+{
+    let before_branch = get_branch_name()?;
+    let before_oid = head_oid()?;
+    f()?;
+    let after_oid = head_oid()?;
+
+    oplog::record(
+        command_name,
+        &before_branch,
+        &before_oid,
+        &after_oid,
+        stash_ref.as_deref(),
+        target_branch,
+    )
+}
+
+fn head_oid() -> Result<String, String> {
+    git_with_output(&["rev-parse", "HEAD"]).map(|o| o.trim().to_owned())
+}
+
+/// `None` means the rebase was aborted (and so nothing was pushed); the
+/// abort was already reported by `sync()` itself, so there's nothing left
+/// to print here.
+fn print_sync_info(sync_info: Option<(usize, usize)>) {
+    match sync_info {
+        None => {}
+        Some((0, 0)) => println!("Already up to date"),
+        Some((ahead, behind)) => {
+            println!("Pushed {} commits and pulled {} commits", ahead, behind)
+        }
     }
 }
 
-fn git(args: &[&str]) -> Result<(), String> {
+pub(crate) fn git(args: &[&str]) -> Result<(), String> {
     git_with_output(args).map(|_| ())
 }
 
-fn git_with_output(args: &[&str]) -> Result<String, String> {
+/// The outcome of a git command that's allowed to fail, along with its
+/// stderr so the caller can tell a merge conflict apart from a real error.
+pub(crate) struct GitAttempt {
+    pub success: bool,
+    pub stderr: String,
+}
+
+/// Run a git command, capturing whether it exited successfully instead of
+/// failing outright. Use this for commands that can stop partway through
+/// with a conflict (`rebase`, `pull --rebase`) rather than `git`/
+/// `git_with_output`, which treat a non-zero exit as success as long as
+/// the process could be spawned.
+pub(crate) fn git_attempt(args: &[&str]) -> Result<GitAttempt, String> {
+    process::Command::new("git")
+        .args(args)
+        .output()
+        .map(|o| GitAttempt {
+            success: o.status.success(),
+            stderr: String::from_utf8_lossy(&o.stderr).into_owned(),
+        })
+        .map_err(|e| e.to_string())
+}
+
+/// Whether a rebase onto `upstream` needs the conflict-resolution flow:
+/// either one is already in progress (resuming), or this attempt stopped
+/// partway through. Returns the stderr to surface if `resolve` turns out
+/// not to find any actual conflicts (`Some("")` when resuming, since there
+/// was no fresh failure to report).
+fn rebase_needs_resolution(upstream: &str) -> Result<Option<String>, String> {
+    if conflict::rebase_in_progress() {
+        return Ok(Some(String::new()));
+    }
+
+    let attempt = git_attempt(&["rebase", upstream])?;
+    Ok(if attempt.success {
+        None
+    } else {
+        Some(attempt.stderr)
+    })
+}
+
+pub(crate) fn git_with_output(args: &[&str]) -> Result<String, String> {
     process::Command::new("git")
         .args(args)
         .output()
@@ -159,21 +247,41 @@ fn git_with_output(args: &[&str]) -> Result<String, String> {
         .map_err(|e| e.to_string())
 }
 
-fn sync() -> Result<(usize, usize), String> {
-    git(&["fetch"])?;
-    let ahead = commits_ahead()?;
-    let behind = commits_behind()?;
-
-    git(&["pull", "--rebase"])?;
-    git(&["push"])?;
+/// Fetch, rebase onto the remote branch, and push. Returns `None` if the
+/// user aborted a conflicted rebase instead of resolving it - in that case
+/// the local branch is unchanged and nothing is pushed.
+fn sync() -> Result<Option<(usize, usize)>, String> {
+    let repo = repository::open()?;
+    repo.fetch("origin")?;
+    let (ahead, behind) = repo.commits_ahead_behind()?;
+
+    // `git pull --rebase` would fetch again itself, over a plain subprocess
+    // using ambient git-CLI credentials rather than `credentials::remote_
+    // callbacks`. The fetch above already brought `origin/<branch>` up to
+    // date through the authenticated git2 backend, so rebase onto that
+    // locally instead - no further network credentials are needed for it.
+    let branch = get_branch_name()?;
+    let upstream = format!("origin/{}", branch);
+    if let Some(failure_stderr) = rebase_needs_resolution(&upstream)? {
+        if conflict::resolve("sync", &failure_stderr)? == conflict::Outcome::Aborted {
+            println!("Sync aborted; local branch left as it was before the rebase");
+            return Ok(None);
+        }
+    }
+    repo.push("origin")?;
 
-    Ok((ahead, behind))
+    Ok(Some((ahead, behind)))
 }
 
 fn switch(branch_name: &str) -> Result<(), String> {
-    stash_branch_changes(false)?;
-    git(&["checkout", branch_name])?;
-    pop_stashed_branch_changes()
+    let stash_ref = stash_branch_changes(false)?;
+    repository::open()?.change_branch(branch_name)?;
+
+    if let Some(stash_ref) = stash_ref {
+        pop_stashed_branch_changes(&stash_ref)?;
+    }
+
+    Ok(())
 }
 
 fn stage(pattern: &str) -> Result<(), String> {
@@ -184,163 +292,32 @@ fn unstage(pattern: &str) -> Result<(), String> {
     git(&["reset", pattern])
 }
 
-fn get_branch_name() -> Result<String, String> {
+pub(crate) fn get_branch_name() -> Result<String, String> {
     git_with_output(&["rev-parse", "--abbrev-ref", "HEAD"]).map(|o| o.trim().to_owned())
 }
 
-fn stash_branch_changes(keep: bool) -> Result<(), String> {
+fn stash_branch_changes(keep: bool) -> Result<Option<String>, String> {
     let branch_name = get_branch_name()?;
     let stash_name = stash_name_for_branch(&branch_name);
 
     stage(".")?;
+    let stash_ref = repository::open()?.stash_push(&stash_name, keep)?;
     if keep {
-        git(&["stash", "push", "-k", "-m", &stash_name])?;
-        unstage(".")
-    } else {
-        git(&["stash", "push", "-m", &stash_name])
+        unstage(".")?;
     }
-}
 
-fn pop_stashed_branch_changes() -> Result<(), String> {
-    let branch_name = get_branch_name()?;
-    let stash_name = stash_name_for_branch(&branch_name);
-
-    let stash = list_stashes()?
-        .into_iter()
-        .find(|s| s.message.contains(&stash_name));
+    Ok(stash_ref)
+}
 
-    if let Some(stash) = &stash {
-        git(&["stash", "pop", &stash.reference])?;
-        unstage(".")
-    } else {
-        Ok(())
-    }
+fn pop_stashed_branch_changes(stash_ref: &str) -> Result<(), String> {
+    repository::open()?.stash_pop(stash_ref)?;
+    unstage(".")
 }
 
 fn stash_name_for_branch(branch_name: &str) -> String {
     format!("gud_local_changes:{}", branch_name)
 }
 
-struct Stash {
-    pub reference: String,
-    pub message: String,
-}
-
-fn list_stashes() -> Result<Vec<Stash>, String> {
-    let output = git_with_output(&["stash", "list"])?;
-
-    let pattern = Regex::new(r"(stash@\{[0-9]+\}): (On [^\n]*)").unwrap();
-
-    Ok(pattern
-        .captures_iter(&output)
-        .map(|capt| Stash {
-            reference: capt.get(1).unwrap().as_str().to_owned(),
-            message: capt.get(2).unwrap().as_str().to_owned(),
-        })
-        .collect())
-}
-
-fn commits_ahead() -> Result<usize, String> {
-    let branch_name = get_branch_name()?;
-    let output = git_with_output(&[
-        "rev-list",
-        &format!("origin/{}..{}", branch_name, branch_name),
-        "--count",
-    ])?;
-
-    output.trim().parse::<usize>().map_err(|e| e.to_string())
-}
-
-fn commits_behind() -> Result<usize, String> {
-    let branch_name = get_branch_name()?;
-    let output = git_with_output(&[
-        "rev-list",
-        &format!("{}..origin/{}", branch_name, branch_name),
-        "--count",
-    ])?;
-
-    output.trim().parse::<usize>().map_err(|e| e.to_string())
-}
-
-#[test]
-fn bar() {
-    let pattern = Regex::new(r"(stash@\{[0-9]+\}): (On [^\n]*)").unwrap();
-    let str = "stash@{0}: On test_branch: gud_local_changes:test_branch
-    stash@{1}: On master: gud_local_changes:master
-    ";
-
-    for c in pattern.captures_iter(str) {
-        println!("{:?}", c);
-    }
-}
-
-// pub fn git_credentials_callback(
-//     user: &str,
-//     user_from_url: Option<&str>,
-//     cred: git2::CredentialType,
-// ) -> Result<git2::Cred, git2::Error> {
-//     let user = user_from_url.unwrap_or("git");
-
-//     if cred.contains(git2::CredentialType::USERNAME) {
-//         return git2::Cred::username(user);
-//     }
-
-//     match std::env::var("GPM_SSH_KEY") {
-//         Ok(k) => {
-//             println!(
-//                 "authenticate with user {} and private key located in {}",
-//                 user, k
-//             );
-//             git2::Cred::ssh_key(user, None, std::path::Path::new(&k), None)
-//         }
-//         _ => Err(git2::Error::from_str(
-//             "unable to get private key from GPM_SSH_KEY",
-//         )),
-//     }
-// }
-
-// fn get_or_init_repo(remote: &str) -> Result<git2::Repository, git2::Error> {
-//     let data_url = match Url::parse(remote) {
-//         Ok(data_url) => data_url,
-//         Err(e) => panic!("failed to parse url: {}", e),
-//     };
-//     let path = std::env::current_dir()
-//         .unwrap()
-//         .join(data_url.host_str().unwrap())
-//         .join(&data_url.path()[1..]);
-
-//     if path.exists() {
-//         println!("use existing repository {}", path.to_str().unwrap());
-//         return git2::Repository::open(path);
-//     }
-
-//     let mut callbacks = git2::RemoteCallbacks::new();
-//     callbacks.credentials(git_credentials_callback);
-
-//     let mut opts = git2::FetchOptions::new();
-//     opts.remote_callbacks(callbacks);
-//     opts.download_tags(git2::AutotagOption::All);
-
-//     let mut builder = git2::build::RepoBuilder::new();
-//     builder.fetch_options(opts);
-//     builder.branch("master");
-
-//     println!(
-//         "start cloning repository {} in {}",
-//         remote,
-//         path.to_str().unwrap()
-//     );
-
-//     match builder.clone(remote, &path) {
-//         Ok(r) => {
-//             println!("repository cloned");
-
-//             Ok(r)
-//         }
-//         Err(e) => Err(e),
-//     }
-// }
-
 fn repository_name(url: &str) -> Result<String, ()> {
     let expr = Regex::new(r"([^/.:]+)(?:\.git)?$").unwrap();
 
@@ -0,0 +1,199 @@
+use std::{
+    env,
+    io::{self, Write},
+    path::PathBuf,
+    process,
+};
+
+use crate::command::{git, git_with_output};
+
+/// A file git has marked as unmerged (both the index and worktree show a
+/// conflict side, e.g. `UU`, `AA`, `AU`).
+pub struct ConflictedFile {
+    pub path: String,
+    pub status: String,
+}
+
+enum FileChoice {
+    Open,
+    Ours,
+    Theirs,
+    Skip,
+}
+
+enum Resolution {
+    Continue,
+    Abort,
+    KeepResolving,
+}
+
+/// What became of an in-progress rebase/pull after [`resolve`] ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// No conflicts were left to resolve, or the user resolved and
+    /// continued them to completion.
+    Continued,
+    /// The user chose to abort; the repository was left as it was before
+    /// the rebase/pull started.
+    Aborted,
+}
+
+/// Whether `.git` currently holds an in-progress rebase (either the merge-
+/// based or the am-based kind), so `gud` can resume it on the next run
+/// instead of starting a fresh one.
+pub fn rebase_in_progress() -> bool {
+    let git_dir = git_dir();
+    git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists()
+}
+
+fn git_dir() -> PathBuf {
+    git_with_output(&["rev-parse", "--git-dir"])
+        .map(|o| PathBuf::from(o.trim()))
+        .unwrap_or_else(|_| PathBuf::from(".git"))
+}
+
+pub fn detect_conflicts() -> Result<Vec<ConflictedFile>, String> {
+    let output = git_with_output(&["status", "--porcelain"])?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let status = line.get(0..2)?;
+            let path = line.get(3..)?.to_owned();
+
+            if is_conflict_status(status) {
+                Some(ConflictedFile {
+                    path,
+                    status: status.to_owned(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+fn is_conflict_status(status: &str) -> bool {
+    matches!(status, "UU" | "AA" | "DD" | "AU" | "UA" | "UD" | "DU")
+}
+
+/// Walk the user through resolving conflicts left behind by `rebase` or
+/// `pull --rebase`, then continue or abort the rebase. Safe to call again
+/// on a repository that's already mid-conflict; it picks up where it left
+/// off by re-reading the unmerged files from `git status`.
+///
+/// `failure_stderr` is the stderr from the command that triggered this
+/// call (empty if it's just resuming an already-in-progress rebase). If
+/// there turn out to be no unmerged files and no rebase in progress, the
+/// command didn't stop on a conflict at all - surface `failure_stderr`
+/// instead of reporting success for a rebase that never happened.
+pub fn resolve(operation: &str, failure_stderr: &str) -> Result<Outcome, String> {
+    if detect_conflicts()?.is_empty() && !rebase_in_progress() {
+        return Err(format!(
+            "{} failed for a reason other than a merge conflict: {}",
+            operation,
+            failure_stderr.trim()
+        ));
+    }
+
+    loop {
+        let conflicts = detect_conflicts()?;
+
+        if conflicts.is_empty() {
+            if rebase_in_progress() {
+                println!("No conflicts remain, continuing {}...", operation);
+                git(&["rebase", "--continue"])?;
+            }
+            return Ok(Outcome::Continued);
+        }
+
+        println!(
+            "{} stopped with {} conflicted file(s):",
+            operation,
+            conflicts.len()
+        );
+        for file in &conflicts {
+            println!("  {}  {}", file.status, file.path);
+        }
+
+        for file in &conflicts {
+            match prompt_file_choice(file)? {
+                FileChoice::Open => open_in_editor(&file.path)?,
+                FileChoice::Ours => {
+                    git(&["checkout", "--ours", &file.path])?;
+                    git(&["add", &file.path])?;
+                }
+                FileChoice::Theirs => {
+                    git(&["checkout", "--theirs", &file.path])?;
+                    git(&["add", &file.path])?;
+                }
+                FileChoice::Skip => {}
+            }
+        }
+
+        match prompt_resolution()? {
+            Resolution::Continue => git(&["rebase", "--continue"])?,
+            Resolution::Abort => {
+                git(&["rebase", "--abort"])?;
+                return Ok(Outcome::Aborted);
+            }
+            Resolution::KeepResolving => {}
+        }
+    }
+}
+
+fn prompt_file_choice(file: &ConflictedFile) -> Result<FileChoice, String> {
+    loop {
+        print!(
+            "{} [o]pen in $EDITOR / [u]se ours / [t]heirs / [s]kip: ",
+            file.path
+        );
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        match read_line()?.trim().to_lowercase().as_str() {
+            "o" | "open" => return Ok(FileChoice::Open),
+            "u" | "ours" => return Ok(FileChoice::Ours),
+            "t" | "theirs" => return Ok(FileChoice::Theirs),
+            "s" | "skip" => return Ok(FileChoice::Skip),
+            _ => println!("Please enter o, u, t, or s"),
+        }
+    }
+}
+
+fn prompt_resolution() -> Result<Resolution, String> {
+    loop {
+        print!("[c]ontinue rebase / [a]bort / [r]echeck remaining files: ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        match read_line()?.trim().to_lowercase().as_str() {
+            "c" | "continue" => return Ok(Resolution::Continue),
+            "a" | "abort" => return Ok(Resolution::Abort),
+            "r" | "recheck" => return Ok(Resolution::KeepResolving),
+            _ => println!("Please enter c, a, or r"),
+        }
+    }
+}
+
+fn read_line() -> Result<String, String> {
+    let mut line = String::new();
+    io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| e.to_string())?;
+    Ok(line)
+}
+
+fn open_in_editor(path: &str) -> Result<(), String> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_owned());
+
+    process::Command::new(editor)
+        .arg(path)
+        .status()
+        .map_err(|e| e.to_string())
+        .and_then(|status| {
+            if status.success() {
+                Ok(())
+            } else {
+                Err("Editor exited with an error".to_owned())
+            }
+        })
+}
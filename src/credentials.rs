@@ -0,0 +1,88 @@
+use std::{cell::Cell, env, path::PathBuf};
+
+use git2::{Cred, CredentialType, RemoteCallbacks};
+
+/// Build the remote callbacks `gud` uses for any `git2` operation that
+/// talks to a remote. Tries, in order: an ssh-agent identity, an explicit
+/// key path (`GUD_SSH_KEY`, falling back to the user's default
+/// `~/.ssh/id_*` files), then whatever the system credential helper has
+/// for username/password or token auth.
+pub fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+
+    // libgit2 calls the credentials callback again if a returned credential
+    // is rejected by the remote - `Cred::ssh_key_from_agent` builds a valid
+    // credential object regardless of whether the agent holds a key the
+    // remote accepts, so without tracking this across calls a rejected
+    // agent identity would just be handed back unchanged forever instead
+    // of falling through to the next method.
+    let tried_agent = Cell::new(false);
+
+    callbacks.credentials(move |url, username_from_url, allowed_types| {
+        authenticate(url, username_from_url, allowed_types, &tried_agent)
+    });
+
+    callbacks
+}
+
+fn authenticate(
+    url: &str,
+    username_from_url: Option<&str>,
+    allowed_types: CredentialType,
+    tried_agent: &Cell<bool>,
+) -> Result<Cred, git2::Error> {
+    let username = username_from_url.unwrap_or("git");
+
+    if allowed_types.contains(CredentialType::USERNAME) {
+        return Cred::username(username);
+    }
+
+    if allowed_types.contains(CredentialType::SSH_KEY) {
+        if !tried_agent.replace(true) {
+            if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                return Ok(cred);
+            }
+        }
+
+        for key_path in ssh_key_candidates() {
+            if key_path.exists() {
+                if let Ok(cred) = Cred::ssh_key(username, None, &key_path, None) {
+                    return Ok(cred);
+                }
+            }
+        }
+    }
+
+    if allowed_types.contains(CredentialType::USER_PASS_PLAINTEXT)
+        || allowed_types.contains(CredentialType::DEFAULT)
+    {
+        if let Ok(config) = git2::Config::open_default() {
+            if let Ok(cred) = Cred::credential_helper(&config, url, Some(username)) {
+                return Ok(cred);
+            }
+        }
+    }
+
+    Err(git2::Error::from_str(&format!(
+        "No usable credentials found for {} (tried ssh-agent, {}, and the git credential helper). \
+         Set GUD_SSH_KEY to a private key path or configure a git credential helper.",
+        url,
+        env::var("GUD_SSH_KEY").unwrap_or_else(|_| "~/.ssh/id_*".to_owned())
+    )))
+}
+
+fn ssh_key_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Ok(path) = env::var("GUD_SSH_KEY") {
+        candidates.push(PathBuf::from(path));
+    }
+
+    if let Some(home) = env::var("HOME").ok().map(PathBuf::from) {
+        for name in ["id_ed25519", "id_rsa", "id_ecdsa"] {
+            candidates.push(home.join(".ssh").join(name));
+        }
+    }
+
+    candidates
+}
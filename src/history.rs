@@ -0,0 +1,154 @@
+use std::process;
+
+use crate::DEBUG;
+
+/// A single commit as parsed out of `git log`.
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub id: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub unix_timestamp: i64,
+    pub parents: Vec<String>,
+    pub subject: String,
+    pub is_merge_commit: bool,
+}
+
+const FIELD_SEP: &str = "\x1f";
+const RECORD_SEP: &str = "\x1e";
+
+pub fn history() -> Result<(), String> {
+    let commits = get_commits(&[])?;
+
+    for commit in &commits {
+        print_commit(commit);
+    }
+
+    Ok(())
+}
+
+/// Commits in `base..head`, newest first (the same order `git log` uses).
+pub fn commits_between(base: &str, head: &str) -> Result<Vec<Commit>, String> {
+    get_commits(&[format!("{}..{}", base, head)])
+}
+
+fn get_commits(extra_args: &[String]) -> Result<Vec<Commit>, String> {
+    let format = format!(
+        "--pretty=format:%H{}%an{}%ae{}%ct{}%P{}%s{}",
+        FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP, RECORD_SEP
+    );
+
+    let output = git_log_output(&format, extra_args)?;
+
+    Ok(output
+        .split(RECORD_SEP)
+        .map(|record| record.trim())
+        .filter(|record| !record.is_empty())
+        .filter_map(parse_commit)
+        .collect())
+}
+
+fn parse_commit(record: &str) -> Option<Commit> {
+    let mut fields = record.split(FIELD_SEP);
+
+    let id = fields.next()?.to_owned();
+    let author_name = fields.next()?.to_owned();
+    let author_email = fields.next()?.to_owned();
+    let unix_timestamp = fields.next()?.parse::<i64>().ok()?;
+    let parents = fields
+        .next()?
+        .split_whitespace()
+        .map(|s| s.to_owned())
+        .collect::<Vec<_>>();
+    let subject = fields.next().unwrap_or("").to_owned();
+    let is_merge_commit = parents.len() > 1;
+
+    Some(Commit {
+        id,
+        author_name,
+        author_email,
+        unix_timestamp,
+        parents,
+        subject,
+        is_merge_commit,
+    })
+}
+
+fn print_commit(commit: &Commit) {
+    let short_hash = &commit.id[..7.min(commit.id.len())];
+    let relative_time = relative_time(commit.unix_timestamp);
+    let merge_flag = if commit.is_merge_commit { " (merge)" } else { "" };
+
+    println!(
+        "* {}  {}  {}  {}{}",
+        short_hash, relative_time, commit.author_name, commit.subject, merge_flag
+    );
+}
+
+fn relative_time(unix_timestamp: i64) -> String {
+    let now = current_unix_timestamp();
+    let delta = (now - unix_timestamp).max(0);
+
+    if delta < 60 {
+        format!("{}s ago", delta)
+    } else if delta < 60 * 60 {
+        format!("{}m ago", delta / 60)
+    } else if delta < 60 * 60 * 24 {
+        format!("{}h ago", delta / (60 * 60))
+    } else if delta < 60 * 60 * 24 * 30 {
+        format!("{}d ago", delta / (60 * 60 * 24))
+    } else {
+        format!("{}mo ago", delta / (60 * 60 * 24 * 30))
+    }
+}
+
+fn current_unix_timestamp() -> i64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn git_log_output(format: &str, extra_args: &[String]) -> Result<String, String> {
+    process::Command::new("git")
+        .arg("log")
+        .arg(format)
+        .args(extra_args)
+        .output()
+        .map(|o| {
+            let out_str = String::from_utf8(o.stdout).unwrap();
+
+            if DEBUG {
+                println!(
+                    "git log {:?} {:?}:\n\t{}\n\t{}",
+                    format,
+                    extra_args,
+                    out_str,
+                    String::from_utf8(o.stderr).unwrap()
+                );
+            }
+
+            out_str
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn parse_commit_test() {
+    let record = format!(
+        "abc123{}Jane Doe{}jane@example.com{}1700000000{}def456 ghi789{}Merge branch 'foo'",
+        FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP, FIELD_SEP
+    );
+
+    let commit = parse_commit(&record).unwrap();
+
+    assert_eq!(commit.id, "abc123");
+    assert_eq!(commit.author_name, "Jane Doe");
+    assert_eq!(commit.author_email, "jane@example.com");
+    assert_eq!(commit.unix_timestamp, 1700000000);
+    assert_eq!(commit.parents, vec!["def456", "ghi789"]);
+    assert!(commit.is_merge_commit);
+    assert_eq!(commit.subject, "Merge branch 'foo'");
+}
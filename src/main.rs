@@ -2,7 +2,13 @@ use clap::Parser;
 use command::Command;
 
 mod command;
+mod conflict;
+mod credentials;
+mod history;
+mod oplog;
 mod repository;
+mod rewrite;
+mod status;
 
 pub const DEBUG: bool = false;
 
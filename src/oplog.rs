@@ -0,0 +1,232 @@
+use std::{fs, path::PathBuf};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::command::{get_branch_name, git, git_with_output};
+
+/// A single mutating command recorded in the operation log, along with
+/// enough state to restore the repository to how it was before (or after)
+/// the command ran.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub seq: i64,
+    pub command: String,
+    pub branch: String,
+    pub before_oid: String,
+    pub after_oid: String,
+    pub stash_ref: Option<String>,
+    /// For `switch`/`branch`, the branch that was switched/created *to*.
+    /// `None` for commands that don't change the current branch.
+    pub target_branch: Option<String>,
+}
+
+/// Record a mutating command in the operation log, advancing the undo
+/// pointer to the newly-inserted entry. Call this *after* the command has
+/// taken effect, passing the branch and HEAD oid from before it ran, the
+/// HEAD oid from after, and (for `switch`/`branch`) the branch it moved to.
+pub fn record(
+    command: &str,
+    branch: &str,
+    before_oid: &str,
+    after_oid: &str,
+    stash_ref: Option<&str>,
+    target_branch: Option<&str>,
+) -> Result<(), String> {
+    let conn = open_connection()?;
+
+    // If the user has undone one or more operations and then performs a
+    // new one, the undone operations are no longer reachable by undo and
+    // must not remain reachable by redo either - otherwise redo would
+    // later re-apply that stale, since-superseded future instead of (or
+    // on top of) the operation being recorded here.
+    let pointer = get_pointer(&conn)?;
+    conn.execute("DELETE FROM operations WHERE seq > ?1", params![pointer])
+        .map_err(|e| e.to_string())?;
+
+    // Insert right after the pointer so the live entries stay contiguous and
+    // undo/redo can step the pointer by one.
+    let seq = pointer + 1;
+    conn.execute(
+        "INSERT INTO operations (seq, command, branch, before_oid, after_oid, stash_ref, target_branch) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![seq, command, branch, before_oid, after_oid, stash_ref, target_branch],
+    )
+    .map_err(|e| e.to_string())?;
+
+    set_pointer(&conn, seq)
+}
+
+/// Undo the most recent not-yet-undone operation, restoring the repository
+/// to the state it was in before that operation ran.
+pub fn undo() -> Result<(), String> {
+    let conn = open_connection()?;
+    let pointer = get_pointer(&conn)?;
+
+    let op = match get_operation(&conn, pointer)? {
+        Some(op) => op,
+        None => return Err("Nothing to undo".to_owned()),
+    };
+
+    restore_to(&op, &op.before_oid, true)?;
+
+    if let Some(stash_ref) = &op.stash_ref {
+        git(&["stash", "apply", stash_ref])?;
+    }
+
+    set_pointer(&conn, pointer - 1)?;
+
+    println!("Undid `{}` on {}", op.command, op.branch);
+    Ok(())
+}
+
+/// Redo the operation that was most recently undone, restoring the
+/// repository to the state it was in after that operation originally ran.
+pub fn redo() -> Result<(), String> {
+    let conn = open_connection()?;
+    let pointer = get_pointer(&conn)?;
+    let next_seq = pointer + 1;
+
+    let op = match get_operation(&conn, next_seq)? {
+        Some(op) => op,
+        None => return Err("Nothing to redo".to_owned()),
+    };
+
+    restore_to(&op, &op.after_oid, false)?;
+
+    set_pointer(&conn, next_seq)?;
+
+    println!("Redid `{}` on {}", op.command, op.branch);
+    Ok(())
+}
+
+/// Restore the repository for `op`, moving towards `before_oid` (`to_before
+/// = true`) or `after_oid` (`to_before = false`).
+///
+/// `switch` and `branch` don't move HEAD on any single branch ref the way
+/// `commit`/`rebase`/`rewrite` do — they move *which branch is current* —
+/// so they're restored by checking out the relevant branch rather than by
+/// resetting a ref. `stage`/`unstage` don't move HEAD at all
+/// (`before_oid == after_oid`), so hard-resetting to that oid would only
+/// serve to blow away unrelated working-tree and index changes; reset just
+/// the index back to `oid` instead.
+fn restore_to(op: &Operation, oid: &str, to_before: bool) -> Result<(), String> {
+    match op.command.as_str() {
+        "stage" | "unstage" => git(&["reset", oid]),
+        "switch" => {
+            let branch = if to_before {
+                &op.branch
+            } else {
+                op.target_branch.as_deref().unwrap_or(&op.branch)
+            };
+            git(&["checkout", branch])
+        }
+        "branch" => {
+            if to_before {
+                git(&["checkout", &op.branch])?;
+                if let Some(new_branch) = &op.target_branch {
+                    git(&["branch", "-D", new_branch])?;
+                }
+                Ok(())
+            } else {
+                let new_branch = op
+                    .target_branch
+                    .as_deref()
+                    .ok_or_else(|| "operation has no recorded target branch".to_owned())?;
+                git(&["checkout", "-b", new_branch])
+            }
+        }
+        _ => {
+            let current_branch = get_branch_name()?;
+
+            if current_branch == op.branch {
+                git(&["reset", "--hard", oid])
+            } else {
+                git(&[
+                    "update-ref",
+                    &format!("refs/heads/{}", op.branch),
+                    oid,
+                ])
+            }
+        }
+    }
+}
+
+fn get_operation(conn: &Connection, seq: i64) -> Result<Option<Operation>, String> {
+    conn.query_row(
+        "SELECT seq, command, branch, before_oid, after_oid, stash_ref, target_branch FROM operations WHERE seq = ?1",
+        params![seq],
+        |row| {
+            Ok(Operation {
+                seq: row.get(0)?,
+                command: row.get(1)?,
+                branch: row.get(2)?,
+                before_oid: row.get(3)?,
+                after_oid: row.get(4)?,
+                stash_ref: row.get(5)?,
+                target_branch: row.get(6)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn get_pointer(conn: &Connection) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT CAST(value AS INTEGER) FROM meta WHERE key = 'pointer'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+    .map(|v| v.unwrap_or(0))
+}
+
+fn set_pointer(conn: &Connection, seq: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES ('pointer', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![seq],
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+fn open_connection() -> Result<Connection, String> {
+    let path = oplog_path()?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS operations (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            command TEXT NOT NULL,
+            branch TEXT NOT NULL,
+            before_oid TEXT NOT NULL,
+            after_oid TEXT NOT NULL,
+            stash_ref TEXT,
+            target_branch TEXT
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(conn)
+}
+
+fn oplog_path() -> Result<PathBuf, String> {
+    let git_dir = git_with_output(&["rev-parse", "--git-dir"])?;
+    Ok(PathBuf::from(git_dir.trim()).join("gud").join("oplog.db"))
+}
@@ -0,0 +1,389 @@
+use std::path::Path;
+
+use git2::{BranchType, FetchOptions, PushOptions, Repository as Git2Repo, Status, StashFlags};
+
+use crate::{
+    command::{git, git_with_output},
+    credentials,
+};
+
+/// A file's status as reported by `Repository::statuses`.
+#[derive(Debug, Clone)]
+pub struct FileStatus {
+    pub path: String,
+    pub staged: bool,
+    pub modified: bool,
+    pub untracked: bool,
+    pub renamed: bool,
+    pub deleted: bool,
+    pub conflicted: bool,
+}
+
+/// Abstraction over the git operations `gud` needs, so the rest of the
+/// codebase isn't coupled to *how* they're performed. [`Git2Repository`]
+/// talks to libgit2 directly; [`ShellRepository`] shells out to the `git`
+/// binary and is kept around as a fallback while the `git2` backend is
+/// still catching up in coverage.
+pub trait Repository {
+    fn branch_name(&self) -> Result<String, String>;
+    fn statuses(&self) -> Result<Vec<FileStatus>, String>;
+    fn commits_ahead_behind(&self) -> Result<(usize, usize), String>;
+    fn branches(&self) -> Result<Vec<String>, String>;
+    fn change_branch(&self, name: &str) -> Result<(), String>;
+    fn create_branch(&self, name: &str) -> Result<(), String>;
+    fn stash_push(&self, message: &str, keep_index: bool) -> Result<Option<String>, String>;
+    fn stash_pop(&self, stash_ref: &str) -> Result<(), String>;
+    fn stash_count(&self) -> Result<usize, String>;
+    fn fetch(&self, remote_name: &str) -> Result<(), String>;
+    fn push(&self, remote_name: &str) -> Result<(), String>;
+}
+
+/// Clone `url` into `into`, authenticating with [`credentials::remote_callbacks`].
+pub fn clone(url: &str, into: &Path) -> Result<(), String> {
+    let mut fetch_opts = FetchOptions::new();
+    fetch_opts.remote_callbacks(credentials::remote_callbacks());
+
+    git2::build::RepoBuilder::new()
+        .fetch_options(fetch_opts)
+        .clone(url, into)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Open the `git2` repository for the current directory, walking up
+/// through parent directories to find `.git` the way every `git`
+/// subprocess (and `ShellRepository`) already does. `Git2Repo::open`
+/// alone only succeeds when the CWD is exactly the repo root.
+fn open_repo() -> Result<Git2Repo, String> {
+    Git2Repo::discover(".").map_err(|e| e.to_string())
+}
+
+/// Open the configured backend for the repository in the current
+/// directory. Defaults to the `git2`-backed implementation; set
+/// `GUD_BACKEND=shell` to fall back to shelling out to `git` instead.
+pub fn open() -> Result<Box<dyn Repository>, String> {
+    match std::env::var("GUD_BACKEND").as_deref() {
+        Ok("shell") => Ok(Box::new(ShellRepository)),
+        _ => Git2Repository::open().map(|repo| Box::new(repo) as Box<dyn Repository>),
+    }
+}
+
+/// Shells out to the `git` binary for every operation, the way `gud` did
+/// before the `git2` backend existed.
+pub struct ShellRepository;
+
+impl Repository for ShellRepository {
+    fn branch_name(&self) -> Result<String, String> {
+        git_with_output(&["rev-parse", "--abbrev-ref", "HEAD"]).map(|o| o.trim().to_owned())
+    }
+
+    fn statuses(&self) -> Result<Vec<FileStatus>, String> {
+        let output = git_with_output(&["status", "--porcelain"])?;
+        Ok(output.lines().filter_map(parse_status_line).collect())
+    }
+
+    fn commits_ahead_behind(&self) -> Result<(usize, usize), String> {
+        let branch = self.branch_name()?;
+
+        let ahead = git_with_output(&[
+            "rev-list",
+            &format!("origin/{}..{}", branch, branch),
+            "--count",
+        ])?;
+        let behind = git_with_output(&[
+            "rev-list",
+            &format!("{}..origin/{}", branch, branch),
+            "--count",
+        ])?;
+
+        Ok((
+            ahead.trim().parse().map_err(|e: std::num::ParseIntError| e.to_string())?,
+            behind.trim().parse().map_err(|e: std::num::ParseIntError| e.to_string())?,
+        ))
+    }
+
+    fn branches(&self) -> Result<Vec<String>, String> {
+        let output = git_with_output(&["branch", "--format=%(refname:short)"])?;
+        Ok(output
+            .lines()
+            .map(|l| l.trim().to_owned())
+            .filter(|l| !l.is_empty())
+            .collect())
+    }
+
+    fn change_branch(&self, name: &str) -> Result<(), String> {
+        git(&["checkout", name])
+    }
+
+    fn create_branch(&self, name: &str) -> Result<(), String> {
+        git(&["checkout", "-b", name])
+    }
+
+    fn stash_push(&self, message: &str, keep_index: bool) -> Result<Option<String>, String> {
+        if keep_index {
+            git(&["stash", "push", "-k", "-m", message])?;
+        } else {
+            git(&["stash", "push", "-m", message])?;
+        }
+
+        let output = git_with_output(&["stash", "list"])?;
+        Ok(output
+            .lines()
+            .find(|l| l.contains(message))
+            .and_then(|l| l.split(':').next())
+            .map(|s| s.trim().to_owned()))
+    }
+
+    fn stash_pop(&self, stash_ref: &str) -> Result<(), String> {
+        git(&["stash", "pop", stash_ref])
+    }
+
+    fn fetch(&self, remote_name: &str) -> Result<(), String> {
+        git(&["fetch", remote_name])
+    }
+
+    fn push(&self, remote_name: &str) -> Result<(), String> {
+        git(&["push", remote_name])
+    }
+
+    fn stash_count(&self) -> Result<usize, String> {
+        let output = git_with_output(&["stash", "list"])?;
+        Ok(output.lines().filter(|l| !l.trim().is_empty()).count())
+    }
+}
+
+/// Parse one line of `git status --porcelain` (v1) output, e.g.
+/// `" M src/main.rs"`, `"?? src/new.rs"`, or `"R  old.rs -> new.rs"`.
+fn parse_status_line(line: &str) -> Option<FileStatus> {
+    let xy = line.get(0..2)?;
+    let path = line.get(3..)?.to_owned();
+    let x = xy.chars().next().unwrap_or('.');
+    let y = xy.chars().nth(1).unwrap_or('.');
+
+    Some(FileStatus {
+        path,
+        staged: x != ' ' && x != '?',
+        modified: y == 'M',
+        untracked: xy == "??",
+        renamed: x == 'R' || y == 'R',
+        deleted: x == 'D' || y == 'D',
+        conflicted: matches!(xy, "UU" | "AA" | "DD" | "AU" | "UA" | "UD" | "DU"),
+    })
+}
+
+/// Talks to the repository directly through libgit2, avoiding a `git`
+/// subprocess (and its human-readable output) for every operation.
+pub struct Git2Repository {
+    inner: Git2Repo,
+}
+
+impl Git2Repository {
+    pub fn open() -> Result<Self, String> {
+        open_repo().map(|inner| Git2Repository { inner })
+    }
+
+    fn stash_index_for(&self, stash_ref: &str) -> Result<usize, String> {
+        let mut repo = open_repo()?;
+        let mut found = None;
+
+        repo.stash_foreach(|index, _message, oid| {
+            if oid.to_string() == stash_ref {
+                found = Some(index);
+                false
+            } else {
+                true
+            }
+        })
+        .map_err(|e| e.to_string())?;
+
+        found.ok_or_else(|| format!("stash {} not found", stash_ref))
+    }
+}
+
+impl Repository for Git2Repository {
+    fn branch_name(&self) -> Result<String, String> {
+        let head = self.inner.head().map_err(|e| e.to_string())?;
+        Ok(head.shorthand().unwrap_or("HEAD").to_owned())
+    }
+
+    fn statuses(&self) -> Result<Vec<FileStatus>, String> {
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true);
+        let statuses = self
+            .inner
+            .statuses(Some(&mut opts))
+            .map_err(|e| e.to_string())?;
+
+        Ok(statuses
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.path()?.to_owned();
+                let status = entry.status();
+
+                Some(FileStatus {
+                    path,
+                    staged: status.intersects(
+                        Status::INDEX_NEW
+                            | Status::INDEX_MODIFIED
+                            | Status::INDEX_DELETED
+                            | Status::INDEX_RENAMED
+                            | Status::INDEX_TYPECHANGE,
+                    ),
+                    modified: status.contains(Status::WT_MODIFIED),
+                    untracked: status.contains(Status::WT_NEW),
+                    renamed: status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED),
+                    deleted: status.intersects(Status::INDEX_DELETED | Status::WT_DELETED),
+                    conflicted: status.contains(Status::CONFLICTED),
+                })
+            })
+            .collect())
+    }
+
+    fn commits_ahead_behind(&self) -> Result<(usize, usize), String> {
+        let head = self.inner.head().map_err(|e| e.to_string())?;
+        let local_oid = head.target().ok_or("HEAD has no target")?;
+        let branch_name = head.shorthand().ok_or("HEAD has no shorthand")?;
+
+        let upstream_oid = self
+            .inner
+            .refname_to_id(&format!("refs/remotes/origin/{}", branch_name))
+            .map_err(|e| e.to_string())?;
+
+        self.inner
+            .graph_ahead_behind(local_oid, upstream_oid)
+            .map_err(|e| e.to_string())
+    }
+
+    fn branches(&self) -> Result<Vec<String>, String> {
+        self.inner
+            .branches(Some(BranchType::Local))
+            .map_err(|e| e.to_string())?
+            .map(|b| {
+                let (branch, _) = b.map_err(|e| e.to_string())?;
+                branch
+                    .name()
+                    .map_err(|e| e.to_string())?
+                    .map(|n| n.to_owned())
+                    .ok_or_else(|| "branch has no name".to_owned())
+            })
+            .collect()
+    }
+
+    fn change_branch(&self, name: &str) -> Result<(), String> {
+        let (object, reference) = self.inner.revparse_ext(name).map_err(|e| e.to_string())?;
+        self.inner
+            .checkout_tree(&object, None)
+            .map_err(|e| e.to_string())?;
+
+        match reference {
+            Some(reference) => self
+                .inner
+                .set_head(reference.name().ok_or("invalid branch ref")?),
+            None => self.inner.set_head_detached(object.id()),
+        }
+        .map_err(|e| e.to_string())
+    }
+
+    fn create_branch(&self, name: &str) -> Result<(), String> {
+        let head_commit = self
+            .inner
+            .head()
+            .and_then(|h| h.peel_to_commit())
+            .map_err(|e| e.to_string())?;
+
+        self.inner
+            .branch(name, &head_commit, false)
+            .map_err(|e| e.to_string())?;
+
+        self.change_branch(name)
+    }
+
+    fn stash_push(&self, message: &str, keep_index: bool) -> Result<Option<String>, String> {
+        let signature = self.inner.signature().map_err(|e| e.to_string())?;
+        let mut flags = StashFlags::DEFAULT;
+        if keep_index {
+            flags |= StashFlags::KEEP_INDEX;
+        }
+
+        // `stash_save` needs `&mut Repository`; open a fresh handle for
+        // this one mutating call rather than widening every method above.
+        let mut repo = open_repo()?;
+        match repo.stash_save(&signature, message, Some(flags)) {
+            Ok(oid) => Ok(Some(oid.to_string())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn stash_pop(&self, stash_ref: &str) -> Result<(), String> {
+        let index = self.stash_index_for(stash_ref)?;
+        let mut repo = open_repo()?;
+        repo.stash_pop(index, None).map_err(|e| e.to_string())
+    }
+
+    fn fetch(&self, remote_name: &str) -> Result<(), String> {
+        let mut remote = self
+            .inner
+            .find_remote(remote_name)
+            .map_err(|e| e.to_string())?;
+
+        let mut fetch_opts = FetchOptions::new();
+        fetch_opts.remote_callbacks(credentials::remote_callbacks());
+
+        remote
+            .fetch(&[] as &[&str], Some(&mut fetch_opts), None)
+            .map_err(|e| e.to_string())
+    }
+
+    fn push(&self, remote_name: &str) -> Result<(), String> {
+        let branch = self.branch_name()?;
+        let mut remote = self
+            .inner
+            .find_remote(remote_name)
+            .map_err(|e| e.to_string())?;
+
+        let mut push_opts = PushOptions::new();
+        push_opts.remote_callbacks(credentials::remote_callbacks());
+
+        let refspec = format!("refs/heads/{}:refs/heads/{}", branch, branch);
+        remote
+            .push(&[refspec.as_str()], Some(&mut push_opts))
+            .map_err(|e| e.to_string())
+    }
+
+    fn stash_count(&self) -> Result<usize, String> {
+        let mut repo = open_repo()?;
+        let mut count = 0;
+        repo.stash_foreach(|_, _, _| {
+            count += 1;
+            true
+        })
+        .map_err(|e| e.to_string())?;
+        Ok(count)
+    }
+}
+
+#[test]
+fn parse_status_line_test() {
+    let modified = parse_status_line(" M src/main.rs").unwrap();
+    assert_eq!(modified.path, "src/main.rs");
+    assert!(!modified.staged);
+    assert!(modified.modified);
+
+    let staged = parse_status_line("M  src/lib.rs").unwrap();
+    assert!(staged.staged);
+    assert!(!staged.modified);
+
+    let untracked = parse_status_line("?? src/new.rs").unwrap();
+    assert!(untracked.untracked);
+    assert!(!untracked.staged);
+
+    let conflicted = parse_status_line("UU src/conflict.rs").unwrap();
+    assert!(conflicted.conflicted);
+
+    let renamed = parse_status_line("R  old.rs -> new.rs").unwrap();
+    assert!(renamed.renamed);
+
+    let deleted = parse_status_line(" D src/gone.rs").unwrap();
+    assert!(deleted.deleted);
+}
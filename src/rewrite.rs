@@ -0,0 +1,292 @@
+use std::{fs, io, process};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Terminal,
+};
+
+use crate::{command::git_with_output, history::Commit};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RebaseAction {
+    Pick,
+    Reword,
+    Squash,
+    Fixup,
+    Drop,
+}
+
+impl RebaseAction {
+    fn cycle(self) -> Self {
+        match self {
+            RebaseAction::Pick => RebaseAction::Reword,
+            RebaseAction::Reword => RebaseAction::Squash,
+            RebaseAction::Squash => RebaseAction::Fixup,
+            RebaseAction::Fixup => RebaseAction::Drop,
+            RebaseAction::Drop => RebaseAction::Pick,
+        }
+    }
+
+    fn keyword(self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup",
+            RebaseAction::Drop => "drop",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            RebaseAction::Pick => "pick  ",
+            RebaseAction::Reword => "reword",
+            RebaseAction::Squash => "squash",
+            RebaseAction::Fixup => "fixup ",
+            RebaseAction::Drop => "drop  ",
+        }
+    }
+}
+
+struct PlanEntry {
+    commit: Commit,
+    action: RebaseAction,
+    new_subject: Option<String>,
+}
+
+enum Mode {
+    Browsing,
+    EditingSubject,
+}
+
+pub fn rewrite() -> Result<(), String> {
+    let base = choose_base()?;
+    let commits = commits_since(&base)?;
+
+    if commits.is_empty() {
+        println!("No commits to rewrite since {}", base);
+        return Ok(());
+    }
+
+    let mut entries: Vec<PlanEntry> = commits
+        .into_iter()
+        .map(|commit| PlanEntry {
+            commit,
+            action: RebaseAction::Pick,
+            new_subject: None,
+        })
+        .collect();
+    // Oldest first, matching the order git-rebase-todo expects.
+    entries.reverse();
+
+    let confirmed = run_tui(&mut entries).map_err(|e| e.to_string())?;
+
+    if !confirmed {
+        println!("Rewrite cancelled");
+        return Ok(());
+    }
+
+    let todo_contents = render_todo(&entries);
+    let temp_path = std::env::temp_dir().join("gud-rebase-todo");
+    fs::write(&temp_path, todo_contents).map_err(|e| e.to_string())?;
+
+    let status = process::Command::new("git")
+        .args(["rebase", "-i", &base])
+        .env("GIT_SEQUENCE_EDITOR", format!("cp {}", temp_path.display()))
+        .status()
+        .map_err(|e| e.to_string())?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err("git rebase -i failed; resolve any conflicts and run `git rebase --continue`".to_owned())
+    }
+}
+
+fn choose_base() -> Result<String, String> {
+    git_with_output(&["merge-base", "HEAD", "@{upstream}"])
+        .map(|o| o.trim().to_owned())
+        .or_else(|_| git_with_output(&["rev-parse", "HEAD~10"]).map(|o| o.trim().to_owned()))
+}
+
+fn commits_since(base: &str) -> Result<Vec<Commit>, String> {
+    crate::history::commits_between(base, "HEAD")
+}
+
+fn render_todo(entries: &[PlanEntry]) -> String {
+    let mut lines = Vec::new();
+
+    for entry in entries {
+        let short_id = &entry.commit.id[..7.min(entry.commit.id.len())];
+
+        // A reword with a subject already captured inline applies it via the
+        // `exec git commit --amend` line below, so the todo line itself only
+        // needs to check the commit out - using the literal `reword` keyword
+        // here would stop the rebase and pop $EDITOR, whose contents would
+        // then be silently clobbered by that `exec` line anyway.
+        let keyword = if entry.action == RebaseAction::Reword && entry.new_subject.is_some() {
+            RebaseAction::Pick.keyword()
+        } else {
+            entry.action.keyword()
+        };
+
+        lines.push(format!("{} {} {}", keyword, short_id, entry.commit.subject));
+
+        if entry.action == RebaseAction::Reword {
+            if let Some(new_subject) = &entry.new_subject {
+                lines.push(format!(
+                    "exec git commit --amend -m {}",
+                    shell_quote(new_subject)
+                ));
+            }
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+fn run_tui(entries: &mut Vec<PlanEntry>) -> io::Result<bool> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let result = event_loop(&mut terminal, entries);
+
+    // Always restore the terminal, even if the event loop returned early on
+    // an error - otherwise a failing `terminal.draw`/`event::read` would
+    // leave the user's terminal stuck in raw/alternate-screen mode.
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    entries: &mut Vec<PlanEntry>,
+) -> io::Result<bool> {
+    let mut selected = 0usize;
+    let mut mode = Mode::Browsing;
+    let mut edit_buffer = String::new();
+    let mut confirmed = false;
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .constraints([Constraint::Min(0), Constraint::Length(3)])
+                .split(f.area());
+
+            let items: Vec<ListItem> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let short_id = &entry.commit.id[..7.min(entry.commit.id.len())];
+                    let subject = entry
+                        .new_subject
+                        .as_deref()
+                        .unwrap_or(&entry.commit.subject);
+                    let line = Line::from(vec![
+                        Span::styled(
+                            format!("{} ", entry.action.label()),
+                            Style::default().fg(Color::Yellow),
+                        ),
+                        Span::raw(format!("{} {}", short_id, subject)),
+                    ]);
+
+                    let style = if i == selected {
+                        Style::default().add_modifier(Modifier::REVERSED)
+                    } else {
+                        Style::default()
+                    };
+
+                    ListItem::new(line).style(style)
+                })
+                .collect();
+
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Rewrite"));
+            f.render_widget(list, chunks[0]);
+
+            let help = match mode {
+                Mode::Browsing => {
+                    "↑/↓ move  J/K reorder  space cycle action  r reword  enter confirm  q cancel"
+                }
+                Mode::EditingSubject => "type new subject, enter to confirm, esc to cancel",
+            };
+            let footer = Paragraph::new(if matches!(mode, Mode::EditingSubject) {
+                format!("> {}", edit_buffer)
+            } else {
+                help.to_owned()
+            })
+            .block(Block::default().borders(Borders::ALL));
+            f.render_widget(footer, chunks[1]);
+        })?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match mode {
+                Mode::Browsing => match key.code {
+                    KeyCode::Up => selected = selected.saturating_sub(1),
+                    KeyCode::Down => selected = (selected + 1).min(entries.len().saturating_sub(1)),
+                    KeyCode::Char('K') if selected > 0 => {
+                        entries.swap(selected, selected - 1);
+                        selected -= 1;
+                    }
+                    KeyCode::Char('J') if selected + 1 < entries.len() => {
+                        entries.swap(selected, selected + 1);
+                        selected += 1;
+                    }
+                    KeyCode::Char(' ') => {
+                        entries[selected].action = entries[selected].action.cycle();
+                    }
+                    KeyCode::Char('r') => {
+                        entries[selected].action = RebaseAction::Reword;
+                        edit_buffer = entries[selected]
+                            .new_subject
+                            .clone()
+                            .unwrap_or_else(|| entries[selected].commit.subject.clone());
+                        mode = Mode::EditingSubject;
+                    }
+                    KeyCode::Enter => {
+                        confirmed = true;
+                        break;
+                    }
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    _ => {}
+                },
+                Mode::EditingSubject => match key.code {
+                    KeyCode::Enter => {
+                        entries[selected].new_subject = Some(edit_buffer.clone());
+                        mode = Mode::Browsing;
+                    }
+                    KeyCode::Esc => mode = Mode::Browsing,
+                    KeyCode::Backspace => {
+                        edit_buffer.pop();
+                    }
+                    KeyCode::Char(c) => edit_buffer.push(c),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    Ok(confirmed)
+}
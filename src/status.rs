@@ -0,0 +1,168 @@
+use crate::repository::{self, FileStatus};
+
+/// Parsed counts from `Repository::statuses`/`commits_ahead_behind`, plus
+/// the number of stashes currently saved.
+#[derive(Debug, Clone, Default)]
+pub struct StatusSummary {
+    pub ahead: usize,
+    pub behind: usize,
+    pub staged: usize,
+    pub modified: usize,
+    pub untracked: usize,
+    pub renamed: usize,
+    pub deleted: usize,
+    pub conflicted: usize,
+    pub stashes: usize,
+}
+
+pub fn status() -> Result<(), String> {
+    let repo = repository::open()?;
+    let branch_name = repo.branch_name()?;
+    let (ahead, behind) = repo.commits_ahead_behind()?;
+    let files = repo.statuses()?;
+    let stashes = repo.stash_count()?;
+    let summary = summarize(&files, ahead, behind, stashes);
+
+    println!("On branch {}", branch_name);
+    println!("{}", render_summary(&summary));
+    println!("{}", render_files(&files));
+
+    Ok(())
+}
+
+fn summarize(files: &[FileStatus], ahead: usize, behind: usize, stashes: usize) -> StatusSummary {
+    let mut summary = StatusSummary {
+        ahead,
+        behind,
+        stashes,
+        ..Default::default()
+    };
+
+    for file in files {
+        if file.staged {
+            summary.staged += 1;
+        }
+        if file.modified {
+            summary.modified += 1;
+        }
+        if file.untracked {
+            summary.untracked += 1;
+        }
+        if file.renamed {
+            summary.renamed += 1;
+        }
+        if file.deleted {
+            summary.deleted += 1;
+        }
+        if file.conflicted {
+            summary.conflicted += 1;
+        }
+    }
+
+    summary
+}
+
+fn render_summary(summary: &StatusSummary) -> String {
+    let mut symbols = Vec::new();
+
+    if summary.ahead > 0 && summary.behind > 0 {
+        symbols.push(format!("⇕{}/{}", summary.ahead, summary.behind));
+    } else if summary.ahead > 0 {
+        symbols.push(format!("⇡{}", summary.ahead));
+    } else if summary.behind > 0 {
+        symbols.push(format!("⇣{}", summary.behind));
+    }
+
+    if summary.conflicted > 0 {
+        symbols.push(format!("={}", summary.conflicted));
+    }
+    if summary.modified > 0 {
+        symbols.push(format!("!{}", summary.modified));
+    }
+    if summary.staged > 0 {
+        symbols.push(format!("+{}", summary.staged));
+    }
+    if summary.renamed > 0 {
+        symbols.push(format!("»{}", summary.renamed));
+    }
+    if summary.deleted > 0 {
+        symbols.push(format!("-{}", summary.deleted));
+    }
+    if summary.untracked > 0 {
+        symbols.push(format!("?{}", summary.untracked));
+    }
+    if summary.stashes > 0 {
+        symbols.push(format!("${}", summary.stashes));
+    }
+
+    if symbols.is_empty() {
+        "clean".to_owned()
+    } else {
+        symbols.join(" ")
+    }
+}
+
+fn render_files(files: &[FileStatus]) -> String {
+    files
+        .iter()
+        .map(|file| {
+            if file.conflicted {
+                format!("  {} (conflicted)", file.path)
+            } else {
+                format!("  {}", file.path)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn summarize_test() {
+    let files = vec![
+        FileStatus {
+            path: "src/main.rs".to_owned(),
+            staged: true,
+            modified: false,
+            untracked: false,
+            renamed: false,
+            deleted: false,
+            conflicted: false,
+        },
+        FileStatus {
+            path: "src/lib.rs".to_owned(),
+            staged: false,
+            modified: true,
+            untracked: false,
+            renamed: false,
+            deleted: false,
+            conflicted: false,
+        },
+        FileStatus {
+            path: "src/new.rs".to_owned(),
+            staged: false,
+            modified: false,
+            untracked: true,
+            renamed: false,
+            deleted: false,
+            conflicted: false,
+        },
+        FileStatus {
+            path: "src/conflict.rs".to_owned(),
+            staged: false,
+            modified: false,
+            untracked: false,
+            renamed: false,
+            deleted: false,
+            conflicted: true,
+        },
+    ];
+
+    let summary = summarize(&files, 2, 1, 0);
+
+    assert_eq!(summary.ahead, 2);
+    assert_eq!(summary.behind, 1);
+    assert_eq!(summary.staged, 1);
+    assert_eq!(summary.modified, 1);
+    assert_eq!(summary.untracked, 1);
+    assert_eq!(summary.conflicted, 1);
+}